@@ -0,0 +1,41 @@
+use anyhow::Result;
+use gpui::AppContext;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::{Settings, SettingsSources};
+
+/// Editor-related settings.
+#[derive(Debug, Clone)]
+pub struct EditorSettings {
+    /// The ordered layout of the editor's right-click context menu, or `None`
+    /// to use the built-in default layout.
+    pub context_menu: Option<Vec<String>>,
+}
+
+/// The serialized form of [`EditorSettings`] as read from a settings file.
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct EditorSettingsContent {
+    /// The ordered list of entries shown in the editor's right-click context
+    /// menu. Each value names a built-in entry (for example `"cut"`,
+    /// `"copy_permalink"`, or `"code_actions"`) or one of the `"go_to"` /
+    /// `"refactor"` groups; the special value `"separator"` inserts a divider.
+    /// When unset, the built-in layout is used.
+    ///
+    /// Default: null
+    #[serde(default)]
+    pub context_menu: Option<Vec<String>>,
+}
+
+impl Settings for EditorSettings {
+    const KEY: Option<&'static str> = Some("editor");
+
+    type FileContent = EditorSettingsContent;
+
+    fn load(sources: SettingsSources<Self::FileContent>, _: &mut AppContext) -> Result<Self> {
+        let context_menu = sources
+            .user
+            .and_then(|content| content.context_menu.clone())
+            .or_else(|| sources.default.context_menu.clone());
+        Ok(Self { context_menu })
+    }
+}